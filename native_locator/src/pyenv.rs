@@ -1,9 +1,11 @@
 // Copyright (c) Microsoft Corporation.
 // Licensed under the MIT License.
 
+use once_cell::sync::Lazy;
 use regex::Regex;
 use std::collections::HashMap;
 use std::fs;
+use std::path::Path;
 use std::path::PathBuf;
 
 use crate::known;
@@ -67,31 +69,59 @@ fn get_pyenv_binary(environment: &dyn known::Environment) -> Option<PathBuf> {
     }
 }
 
-fn get_pyenv_version(folder_name: &String) -> Option<String> {
-    // Stable Versions = like 3.10.10
-    let python_regex = Regex::new(r"^(\d+\.\d+\.\d+)$").unwrap();
-    match python_regex.captures(&folder_name) {
+// `implementation` is `None` for plain CPython (including free-threaded `t`
+// builds), and the pyenv-recognized name (`pypy`, `graalpy`, `stackless`) for
+// alternative interpreters, so callers can tell them apart.
+struct PyenvVersionInfo {
+    implementation: Option<String>,
+    version: String,
+}
+
+// Compiled once per process instead of on every directory entry in
+// `list_pyenv_environments`.
+// Stable versions are `major.minor` with an optional patch component, and an
+// optional trailing `t` for free-threaded builds (e.g. `3.13t`, `3.13.0t`).
+static STABLE_VERSION_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(\d+\.\d+(?:\.\d+)?t?)$").unwrap());
+static DEV_VERSION_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(\d+\.\d+t?-dev)$").unwrap());
+static PRE_RELEASE_VERSION_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(\d+\.\d+.\d+\w\d+)").unwrap());
+static ALTERNATIVE_IMPLEMENTATION_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(pypy|graalpy|stackless)(\d+\.\d+)?-(\d+\.\d+(?:\.\d+)?)").unwrap());
+static PY_VERSION_HEADER_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"#define\s+PY_VERSION\s+"([^"]+)""#).unwrap());
+
+fn get_pyenv_version(folder_name: &String) -> Option<PyenvVersionInfo> {
+    // Stable Versions = like 3.10.10 or 3.13t (free-threaded)
+    match STABLE_VERSION_REGEX.captures(&folder_name) {
         Some(captures) => match captures.get(1) {
-            Some(version) => Some(version.as_str().to_string()),
+            Some(version) => Some(PyenvVersionInfo {
+                implementation: None,
+                version: version.as_str().to_string(),
+            }),
             None => None,
         },
         None => {
-            // Dev Versions = like 3.10-dev
-            let python_regex = Regex::new(r"^(\d+\.\d+-dev)$").unwrap();
-            match python_regex.captures(&folder_name) {
+            // Dev Versions = like 3.10-dev or 3.13t-dev
+            match DEV_VERSION_REGEX.captures(&folder_name) {
                 Some(captures) => match captures.get(1) {
-                    Some(version) => Some(version.as_str().to_string()),
+                    Some(version) => Some(PyenvVersionInfo {
+                        implementation: None,
+                        version: version.as_str().to_string(),
+                    }),
                     None => None,
                 },
                 None => {
                     // Alpha, rc Versions = like 3.10.0a3
-                    let python_regex = Regex::new(r"^(\d+\.\d+.\d+\w\d+)").unwrap();
-                    match python_regex.captures(&folder_name) {
+                    match PRE_RELEASE_VERSION_REGEX.captures(&folder_name) {
                         Some(captures) => match captures.get(1) {
-                            Some(version) => Some(version.as_str().to_string()),
+                            Some(version) => Some(PyenvVersionInfo {
+                                implementation: None,
+                                version: version.as_str().to_string(),
+                            }),
                             None => None,
                         },
-                        None => None,
+                        None => get_alternative_implementation_version(&folder_name),
                     }
                 }
             }
@@ -99,25 +129,129 @@ fn get_pyenv_version(folder_name: &String) -> Option<String> {
     }
 }
 
+// Alternative pyenv-installed interpreters: pypy3.10-7.3.15, graalpy-23.1.0,
+// stackless-3.7.5.
+fn get_alternative_implementation_version(folder_name: &str) -> Option<PyenvVersionInfo> {
+    let captures = ALTERNATIVE_IMPLEMENTATION_REGEX.captures(folder_name)?;
+    let implementation = captures.get(1)?.as_str().to_string();
+    // PyPy folder names embed the CPython compatibility version (e.g. `3.10`)
+    // ahead of PyPy's own release version; prefer that for version matching,
+    // falling back to the implementation's own version for GraalPy/Stackless.
+    let version = captures
+        .get(2)
+        .or_else(|| captures.get(3))
+        .map(|m| m.as_str().to_string())?;
+    Some(PyenvVersionInfo {
+        implementation: Some(implementation),
+        version,
+    })
+}
+
+// Some installs (renamed folders, custom builds) don't have a version-shaped
+// folder name, so `get_pyenv_version` alone would drop them. Fall back to
+// reading the version out of the interpreter's own metadata.
+fn get_version_from_patchlevel_h(install_dir: &Path) -> Option<String> {
+    let include_dir = fs::read_dir(install_dir.join("include")).ok()?;
+    for entry in include_dir.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !name.starts_with("python3.") {
+            continue;
+        }
+        let contents = fs::read_to_string(entry.path().join("patchlevel.h")).ok()?;
+        if let Some(captures) = PY_VERSION_HEADER_REGEX.captures(&contents) {
+            return captures.get(1).map(|m| m.as_str().to_string());
+        }
+    }
+    None
+}
+
+// Only `patchlevel.h` is used as a fallback here: a directory with a
+// `pyvenv.cfg` is a virtualenv, not a base install, and must be routed to
+// `get_virtual_env_environment` instead of being reported as a bare pyenv
+// Python (see `get_pyenv_environment`).
+fn get_folder_version(path: &Path) -> Option<PyenvVersionInfo> {
+    let folder_name = path.file_name()?.to_string_lossy().to_string();
+    get_pyenv_version(&folder_name).or_else(|| {
+        get_version_from_patchlevel_h(path).map(|version| PyenvVersionInfo {
+            implementation: None,
+            version,
+        })
+    })
+}
+
+// A pyenv install's `bin` directory typically has `python`, `python3` and
+// `python3.x` all symlinked to the same interpreter. Collect every one of
+// these aliases so `pick_canonical_executable` can choose the shortest name.
+fn get_python_symlinks(bin_dir: &Path) -> Vec<PathBuf> {
+    let mut symlinks = vec![];
+    let Ok(entries) = fs::read_dir(bin_dir) else {
+        return symlinks;
+    };
+    for entry in entries.flatten() {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        if file_name != "python" && !file_name.starts_with("python3") {
+            continue;
+        }
+        symlinks.push(entry.path());
+    }
+    symlinks
+}
+
+// Prefer the shortest alias name (e.g. `python3` over `python3.12.1`) among
+// the candidates, then canonicalize that single winner's directory. Canonicalizing
+// only the directory, after picking, means the selection actually prefers
+// the short alias instead of always collapsing to the resolved real binary;
+// canonicalizing the directory still ensures the same interpreter reached
+// through different directory aliases (e.g. a pyenv-virtualenv's top-level
+// symlink vs. its `versions/<base>/envs/<name>` path) resolves to the same
+// path for keying purposes.
+fn pick_canonical_executable(symlinks: &[PathBuf], fallback: &PathBuf) -> PathBuf {
+    let shortest = symlinks
+        .iter()
+        .filter(|p| p.is_file())
+        .min_by_key(|p| {
+            p.file_name()
+                .map(|n| n.to_string_lossy().len())
+                .unwrap_or(usize::MAX)
+        })
+        .cloned()
+        .unwrap_or_else(|| fallback.clone());
+    // Resolve only the containing directory, so the alias name itself is kept.
+    let resolved = match (shortest.parent(), shortest.file_name()) {
+        (Some(dir), Some(name)) => fs::canonicalize(dir).ok().map(|d| d.join(name)),
+        _ => None,
+    };
+    resolved.unwrap_or(shortest)
+}
+
+fn resolve_canonical_executable(executable: &PathBuf) -> PathBuf {
+    let bin_dir = executable.parent().unwrap_or(executable.as_path());
+    let symlinks = get_python_symlinks(bin_dir);
+    pick_canonical_executable(&symlinks, executable)
+}
+
 fn get_pure_python_environment(
     executable: &PathBuf,
     path: &PathBuf,
     manager: &Option<EnvManager>,
 ) -> Option<PythonEnvironment> {
-    let version = get_pyenv_version(&path.file_name().unwrap().to_string_lossy().to_string())?;
+    let version_info = get_folder_version(path)?;
+    let canonical_executable = resolve_canonical_executable(executable);
+    // Non-CPython implementations don't have a dedicated category, so surface
+    // them through the display name instead (e.g. `pypy3.10-7.3.15`).
+    let name = version_info
+        .implementation
+        .as_ref()
+        .map(|_| path.file_name().unwrap().to_string_lossy().to_string());
     Some(messaging::PythonEnvironment::new(
-        None,
-        Some(executable.clone()),
+        name,
+        Some(canonical_executable.clone()),
         messaging::PythonEnvironmentCategory::Pyenv,
-        Some(version),
+        Some(version_info.version),
         Some(path.clone()),
         Some(path.clone()),
         manager.clone(),
-        Some(vec![executable
-            .clone()
-            .into_os_string()
-            .into_string()
-            .unwrap()]),
+        Some(vec![canonical_executable.to_string_lossy().to_string()]),
     ))
 }
 
@@ -128,28 +262,193 @@ fn get_virtual_env_environment(
 ) -> Option<messaging::PythonEnvironment> {
     let pyenv_cfg = find_and_parse_pyvenv_cfg(executable)?;
     let folder_name = path.file_name().unwrap().to_string_lossy().to_string();
+    let canonical_executable = resolve_canonical_executable(executable);
     Some(messaging::PythonEnvironment::new(
         Some(folder_name),
-        Some(executable.clone()),
+        Some(canonical_executable.clone()),
         messaging::PythonEnvironmentCategory::PyenvVirtualEnv,
         Some(pyenv_cfg.version),
         Some(path.clone()),
         Some(path.clone()),
         manager.clone(),
-        Some(vec![executable
-            .clone()
-            .into_os_string()
-            .into_string()
-            .unwrap()]),
+        Some(vec![canonical_executable.to_string_lossy().to_string()]),
+    ))
+}
+
+// A directory with a `pyvenv.cfg` is a virtualenv, not a base install, so it
+// must be tried as one first; otherwise `get_pure_python_environment`'s
+// `patchlevel.h`/folder-name fallbacks can misreport it as a bare pyenv
+// Python with no name.
+fn get_pyenv_environment(
+    executable: &PathBuf,
+    path: &PathBuf,
+    manager: &Option<EnvManager>,
+) -> Option<PythonEnvironment> {
+    if find_and_parse_pyvenv_cfg(executable).is_some() {
+        get_virtual_env_environment(executable, path, manager)
+            .or_else(|| get_pure_python_environment(executable, path, manager))
+    } else {
+        get_pure_python_environment(executable, path, manager)
+            .or_else(|| get_virtual_env_environment(executable, path, manager))
+    }
+}
+
+// A pyenv-virtualenv environment at `versions/<base>/envs/<name>`; reported
+// with the owning base version directory as its prefix.
+fn get_pyenv_virtualenv_environment(
+    name: String,
+    env_path: &Path,
+    base_version_path: &Path,
+    manager: &Option<EnvManager>,
+) -> Option<messaging::PythonEnvironment> {
+    let executable = find_python_binary_path(env_path)?;
+    let pyenv_cfg = find_and_parse_pyvenv_cfg(&executable)?;
+    let canonical_executable = resolve_canonical_executable(&executable);
+    Some(messaging::PythonEnvironment::new(
+        Some(name),
+        Some(canonical_executable.clone()),
+        messaging::PythonEnvironmentCategory::PyenvVirtualEnv,
+        Some(pyenv_cfg.version),
+        Some(base_version_path.to_path_buf()),
+        Some(env_path.to_path_buf()),
+        manager.clone(),
+        Some(vec![canonical_executable.to_string_lossy().to_string()]),
+    ))
+}
+
+const PYENV_SHIMS_DIR: &str = "shims";
+
+fn is_pyenv_shim(python_executable: &Path, pyenv_dir: &Path) -> bool {
+    python_executable.starts_with(pyenv_dir.join(PYENV_SHIMS_DIR))
+}
+
+// Walks upward from `start_dir` looking for a `.python-version` file, the same
+// way `pyenv` itself resolves the active version for a directory.
+// See https://github.com/pyenv/pyenv#choosing-the-python-version
+fn find_python_version_file(start_dir: &Path) -> Option<String> {
+    let mut dir = Some(start_dir.to_path_buf());
+    while let Some(current) = dir {
+        let version_file = current.join(".python-version");
+        if let Ok(contents) = fs::read_to_string(&version_file) {
+            let version = contents.lines().next().unwrap_or_default().trim();
+            if !version.is_empty() {
+                return Some(version.to_string());
+            }
+        }
+        dir = current.parent().map(|p| p.to_path_buf());
+    }
+    None
+}
+
+// Replicates pyenv's version-selection algorithm: `PYENV_VERSION` wins, then the
+// nearest `.python-version` file walking up from the current directory, then the
+// global `$PYENV_ROOT/version` file.
+fn get_active_pyenv_version(
+    environment: &dyn known::Environment,
+    pyenv_dir: &Path,
+) -> Option<String> {
+    if let Some(version) = environment.get_env_var("PYENV_VERSION".to_string()) {
+        return Some(version);
+    }
+
+    if let Some(cwd) = environment.get_current_directory() {
+        if let Some(version) = find_python_version_file(&cwd) {
+            return Some(version);
+        }
+    }
+
+    let global_version_file = pyenv_dir.join("version");
+    let contents = fs::read_to_string(&global_version_file).ok()?;
+    let version = contents.lines().next().unwrap_or_default().trim();
+    if version.is_empty() {
+        None
+    } else {
+        Some(version.to_string())
+    }
+}
+
+// pyenv can install full conda distributions (miniconda/anaconda/miniforge)
+// into `versions/<name>`. These carry their own conda metadata and named
+// sub-environments, so they're detected and reported as conda installs
+// rather than bare pyenv Pythons.
+const CONDA_DIST_PREFIXES: [&str; 3] = ["miniconda3-", "anaconda3-", "miniforge3-"];
+
+fn is_conda_install_dir(path: &Path) -> bool {
+    let folder_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    CONDA_DIST_PREFIXES
+        .iter()
+        .any(|prefix| folder_name.starts_with(prefix))
+        || path.join("conda-meta").is_dir()
+}
+
+fn get_pyenv_conda_environment(
+    name: Option<String>,
+    install_dir: &Path,
+    conda_manager: &Option<EnvManager>,
+) -> Option<PythonEnvironment> {
+    let executable = find_python_binary_path(install_dir)?;
+    let version = get_folder_version(install_dir).map(|v| v.version);
+    Some(messaging::PythonEnvironment::new(
+        name,
+        Some(executable.clone()),
+        messaging::PythonEnvironmentCategory::Conda,
+        version,
+        Some(install_dir.to_path_buf()),
+        Some(install_dir.to_path_buf()),
+        conda_manager.clone(),
+        Some(vec![executable.to_string_lossy().to_string()]),
     ))
 }
 
+// Delegates to conda-style discovery: the distribution root is the base
+// environment, and each entry under `envs/` is a named conda environment.
+fn get_pyenv_conda_environments(
+    install_dir: &Path,
+) -> (Option<EnvManager>, Vec<PythonEnvironment>) {
+    let conda_manager = Some(messaging::EnvManager::new(
+        install_dir.join("bin").join("conda"),
+        None,
+        EnvManagerType::Conda,
+    ));
+
+    let mut envs = vec![];
+    if let Some(base_env) = get_pyenv_conda_environment(None, install_dir, &conda_manager) {
+        envs.push(base_env);
+    }
+
+    if let Ok(entries) = fs::read_dir(install_dir.join("envs")) {
+        for entry in entries.flatten() {
+            let env_path = entry.path();
+            if !env_path.is_dir() {
+                continue;
+            }
+            let name = env_path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string());
+            if let Some(env) = get_pyenv_conda_environment(name, &env_path, &conda_manager) {
+                envs.push(env);
+            }
+        }
+    }
+
+    // Don't surface a manager for a distribution where no environment was
+    // actually resolved — it would point at a possibly nonexistent `conda`
+    // binary for zero reported environments.
+    let conda_manager = if envs.is_empty() { None } else { conda_manager };
+
+    (conda_manager, envs)
+}
+
 pub fn list_pyenv_environments(
     manager: &Option<EnvManager>,
     environment: &dyn known::Environment,
-) -> Option<Vec<messaging::PythonEnvironment>> {
+) -> Option<(Vec<messaging::PythonEnvironment>, Vec<EnvManager>)> {
     let pyenv_dir = get_pyenv_dir(environment)?;
     let mut envs: Vec<messaging::PythonEnvironment> = vec![];
+    let mut conda_managers: Vec<EnvManager> = vec![];
     let versions_dir = PathBuf::from(&pyenv_dir)
         .join("versions")
         .into_os_string()
@@ -162,25 +461,58 @@ pub fn list_pyenv_environments(
             if !path.is_dir() {
                 continue;
             }
+
+            if is_conda_install_dir(&path) {
+                let (conda_manager, conda_envs) = get_pyenv_conda_environments(&path);
+                if let Some(conda_manager) = conda_manager {
+                    conda_managers.push(conda_manager);
+                }
+                envs.extend(conda_envs);
+                continue;
+            }
+
             if let Some(executable) = find_python_binary_path(&path) {
-                match get_pure_python_environment(&executable, &path, manager) {
-                    Some(env) => envs.push(env),
-                    None => match get_virtual_env_environment(&executable, &path, manager) {
-                        Some(env) => envs.push(env),
-                        None => (),
-                    },
+                if let Some(env) = get_pyenv_environment(&executable, &path, manager) {
+                    envs.push(env);
+                }
+            }
+
+            // pyenv-virtualenv creates environments at `versions/<base>/envs/<name>`,
+            // which are also symlinked as top-level `versions/<name>` entries. The
+            // symlinked alias resolves to the same canonical executable as the one
+            // found here, so the HashMap in `gather` naturally dedupes the two.
+            if let Ok(entries) = fs::read_dir(path.join("envs")) {
+                for entry in entries.flatten() {
+                    let env_path = entry.path();
+                    if !env_path.is_dir() {
+                        continue;
+                    }
+                    let Some(name) = env_path
+                        .file_name()
+                        .map(|n| n.to_string_lossy().to_string())
+                    else {
+                        continue;
+                    };
+                    if let Some(env) =
+                        get_pyenv_virtualenv_environment(name, &env_path, &path, manager)
+                    {
+                        envs.push(env);
+                    }
                 }
             }
         }
     }
 
-    Some(envs)
+    Some((envs, conda_managers))
 }
 
 pub struct PyEnv<'a> {
     pub environments: HashMap<String, PythonEnvironment>,
     pub environment: &'a dyn Environment,
     pub manager: Option<EnvManager>,
+    // Conda distributions installed through pyenv (miniconda/anaconda/miniforge)
+    // are reported with their own conda `EnvManager`, separate from `manager`.
+    pub conda_managers: Vec<EnvManager>,
 }
 
 impl PyEnv<'_> {
@@ -189,6 +521,7 @@ impl PyEnv<'_> {
             environments: HashMap::new(),
             environment,
             manager: None,
+            conda_managers: vec![],
         }
     }
 }
@@ -204,6 +537,19 @@ impl Locator for PyEnv<'_> {
         false
     }
 
+    fn resolve(&self, env: &PythonEnv) -> Option<PythonEnvironment> {
+        let pyenv_dir = get_pyenv_dir(self.environment)?;
+        if !is_pyenv_shim(&env.executable, &pyenv_dir) {
+            return None;
+        }
+
+        let version = get_active_pyenv_version(self.environment, &pyenv_dir)?;
+        let path = pyenv_dir.join("versions").join(version);
+        let executable = find_python_binary_path(&path)?;
+
+        get_pyenv_environment(&executable, &path, &self.manager)
+    }
+
     fn gather(&mut self) -> Option<()> {
         let manager = match get_pyenv_binary(self.environment) {
             Some(pyenv_binary) => Some(messaging::EnvManager::new(
@@ -215,7 +561,9 @@ impl Locator for PyEnv<'_> {
         };
         self.manager = manager.clone();
 
-        for env in list_pyenv_environments(&manager, self.environment)? {
+        let (envs, conda_managers) = list_pyenv_environments(&manager, self.environment)?;
+        self.conda_managers = conda_managers;
+        for env in envs {
             self.environments.insert(
                 env.python_executable_path
                     .as_ref()
@@ -233,6 +581,9 @@ impl Locator for PyEnv<'_> {
         if let Some(manager) = &self.manager {
             reporter.report_environment_manager(manager.clone());
         }
+        for conda_manager in &self.conda_managers {
+            reporter.report_environment_manager(conda_manager.clone());
+        }
         for env in self.environments.values() {
             reporter.report_environment(env.clone());
         }